@@ -1,12 +1,17 @@
-use adafruit_motorkit::{dc::DcMotor, MotorControl};
+use adafruit_motorkit::control::{MotorControl, MotorId};
+use adafruit_motorkit::Motor;
 use std::error::Error;
 use std::thread;
 use std::time::Duration;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let mut motor_ctrl = MotorControl::try_new(None)?;
-    motor_ctrl.set_dc_motor(DcMotor::Motor1, 0.5)?;
+    motor_ctrl.add_dc_motor(0, Motor::Motor1)?;
+    let motor1 = MotorId::new(0, Motor::Motor1);
+
+    motor_ctrl.set_dc_motor(motor1, 0.5)?;
     thread::sleep(Duration::from_secs(5));
-    motor_ctrl.set_dc_motor(DcMotor::Motor1, 0.0)?;
+    motor_ctrl.set_dc_motor(motor1, 0.0)?;
+    motor_ctrl.stop_all()?;
     Ok(())
 }
@@ -0,0 +1,168 @@
+use crate::dc::DcMotor;
+use crate::stepper::{StepDirection, StepStyle, StepperMotor, StepperWiring};
+use crate::{init_pwm, init_pwm_with, Motor, MotorError};
+use linux_embedded_hal::I2cdev;
+use pwm_pca9685::{Pca9685, SlaveAddr};
+use std::collections::HashMap;
+
+/// Identifies a single DC or stepper motor in a `MotorControl` stack, as
+/// the index of its hat (in the order the hat's address was given to
+/// `MotorControl::try_new`/`try_new_stacked`) plus which `Motor` slot on
+/// that hat.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct MotorId {
+    pub hat: usize,
+    pub motor: Motor,
+}
+
+impl MotorId {
+    /// Builds a `MotorId` for `motor` on the hat at index `hat`.
+    pub fn new(hat: usize, motor: Motor) -> Self {
+        Self { hat, motor }
+    }
+}
+
+enum ConfiguredMotor {
+    Dc(DcMotor),
+    Stepper(StepperMotor),
+}
+
+/// A facade that owns the `Pca9685` for one or more stacked Motor HATs
+/// (each at its own I2C address, set via its 6 address-select jumpers)
+/// and the motors configured on them, so callers can drive any motor on
+/// any hat through a single handle.
+pub struct MotorControl {
+    hats: Vec<Pca9685<I2cdev>>,
+    motors: HashMap<MotorId, ConfiguredMotor>,
+}
+
+impl MotorControl {
+    /// Attempts to initialize a `MotorControl` for a single Motor HAT at
+    /// the default address (0x60), mirroring `init_pwm`. For stacked
+    /// hats, use `try_new_stacked`.
+    pub fn try_new(i2c: Option<I2cdev>) -> Result<Self, MotorError> {
+        let pwm = init_pwm(i2c)?;
+        Ok(Self {
+            hats: vec![pwm],
+            motors: HashMap::new(),
+        })
+    }
+
+    /// Attempts to initialize a `MotorControl` stack, one hat per address
+    /// in `addresses`, all running at `freq_hz`. Each hat opens its own
+    /// connection to `i2c_path` (default `/dev/i2c-1`), since stacked hats
+    /// share the same Linux I2C bus but are distinguished on the wire by
+    /// address.
+    pub fn try_new_stacked(
+        i2c_path: Option<&str>,
+        addresses: Vec<SlaveAddr>,
+        freq_hz: f32,
+    ) -> Result<Self, MotorError> {
+        let path = i2c_path.unwrap_or("/dev/i2c-1");
+        let mut hats = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let i2c = I2cdev::new(path).map_err(|_| MotorError::I2cError)?;
+            hats.push(init_pwm_with(Some(i2c), freq_hz, address)?);
+        }
+        Ok(Self {
+            hats,
+            motors: HashMap::new(),
+        })
+    }
+
+    /// Configures `motor` on the hat at index `hat` as a DC motor, so it
+    /// can be driven via `set_dc_motor`.
+    pub fn add_dc_motor(
+        &mut self,
+        hat: usize,
+        motor: Motor,
+    ) -> Result<(), MotorError> {
+        let pwm =
+            self.hats.get_mut(hat).ok_or(MotorError::InvalidMotorError)?;
+        let dc_motor = DcMotor::try_new(pwm, motor)?;
+        self.motors
+            .insert(MotorId::new(hat, motor), ConfiguredMotor::Dc(dc_motor));
+        Ok(())
+    }
+
+    /// Configures `motor` on the hat at index `hat` as a stepper motor, so
+    /// it can be driven via `step`.
+    pub fn add_stepper_motor(
+        &mut self,
+        hat: usize,
+        motor: Motor,
+        microsteps: Option<i32>,
+        wiring: StepperWiring,
+    ) -> Result<(), MotorError> {
+        let pwm =
+            self.hats.get_mut(hat).ok_or(MotorError::InvalidMotorError)?;
+        let stepper = StepperMotor::try_new(pwm, motor, microsteps, wiring)?;
+        self.motors.insert(
+            MotorId::new(hat, motor),
+            ConfiguredMotor::Stepper(stepper),
+        );
+        Ok(())
+    }
+
+    /// Sets the throttle for the DC motor at `id`. Valid throttle values
+    /// are in the range [-1.0, 1.0].
+    pub fn set_dc_motor(
+        &mut self,
+        id: MotorId,
+        throttle: f32,
+    ) -> Result<(), MotorError> {
+        let pwm = self
+            .hats
+            .get_mut(id.hat)
+            .ok_or(MotorError::InvalidMotorError)?;
+        match self.motors.get_mut(&id) {
+            Some(ConfiguredMotor::Dc(dc_motor)) => {
+                dc_motor.set_throttle(pwm, throttle)
+            }
+            _ => Err(MotorError::InvalidMotorError),
+        }
+    }
+
+    /// Steps the stepper motor at `id` once in `step_dir` with
+    /// `step_style`.
+    pub fn step(
+        &mut self,
+        id: MotorId,
+        step_dir: StepDirection,
+        step_style: StepStyle,
+    ) -> Result<(), MotorError> {
+        let pwm = self
+            .hats
+            .get_mut(id.hat)
+            .ok_or(MotorError::InvalidMotorError)?;
+        match self.motors.get_mut(&id) {
+            Some(ConfiguredMotor::Stepper(stepper)) => {
+                stepper.step_once(pwm, step_dir, step_style)
+            }
+            _ => Err(MotorError::InvalidMotorError),
+        }
+    }
+
+    /// Stops every motor configured across every hat in the stack. Being the
+    /// safety/shutdown path, this attempts every motor even if an earlier
+    /// one fails, returning the first error encountered (if any) only after
+    /// every motor has been given a chance to stop.
+    pub fn stop_all(&mut self) -> Result<(), MotorError> {
+        let hats = &mut self.hats;
+        let mut first_err = None;
+        for (id, motor) in self.motors.iter_mut() {
+            let pwm = &mut hats[id.hat];
+            let result = match motor {
+                ConfiguredMotor::Dc(dc_motor) => dc_motor.stop(pwm),
+                ConfiguredMotor::Stepper(stepper) => stepper.stop(pwm),
+            };
+            if let Err(err) = result {
+                first_err.get_or_insert(err);
+            }
+        }
+        match first_err {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,6 +1,5 @@
 use crate::{Motor, MotorError};
-use hal::I2cdev;
-use linux_embedded_hal as hal;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
 use pwm_pca9685::{Channel, Pca9685};
 use std::cmp::Ordering;
 
@@ -42,17 +41,33 @@ pub struct DcChannels {
     backward_channel: Channel,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The H-bridge decay mode used while driving a `DcMotor`.
+pub enum DecayMode {
+    /// The non-driven channel is left off during the idle phase of each PWM
+    /// cycle, letting the motor coast.
+    Fast,
+    /// The non-driven channel is driven at `4095 - duty_cycle` during the
+    /// idle phase of each PWM cycle, shorting both windings for active
+    /// braking.
+    Slow,
+}
+
 /// A structure to initialize and control a DC motor.
 pub struct DcMotor {
     channels: DcChannels,
+    decay_mode: DecayMode,
 }
 
 impl DcMotor {
     /// Attempts to initialize a DC motor.
-    pub fn try_new(
-        pwm: &mut Pca9685<I2cdev>,
+    pub fn try_new<I2C, E>(
+        pwm: &mut Pca9685<I2C>,
         motor: Motor,
-    ) -> Result<Self, MotorError> {
+    ) -> Result<Self, MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
         let channels =
             get_dc_channels_map(motor).ok_or(MotorError::InvalidMotorError)?;
 
@@ -67,29 +82,49 @@ impl DcMotor {
         // Set the reference channel to run at full blast.
         pwm.set_channel_off(channels.ref_channel, 4095)
             .map_err(|_| MotorError::ChannelError)?;
-        Ok(Self { channels })
+        Ok(Self {
+            channels,
+            decay_mode: DecayMode::Fast,
+        })
+    }
+
+    /// Sets the H-bridge decay mode used for this motor. Defaults to
+    /// `DecayMode::Fast` (coasting).
+    pub fn set_decay_mode(&mut self, decay_mode: DecayMode) {
+        self.decay_mode = decay_mode;
     }
 
     /// Sets the throttle for the motor. Valid throttle values are in the
     /// range [-1.0, 1.0].
-    pub fn set_throttle(
+    pub fn set_throttle<I2C, E>(
         &mut self,
-        pwm: &mut Pca9685<I2cdev>,
+        pwm: &mut Pca9685<I2C>,
         throttle: f32,
-    ) -> Result<(), MotorError> {
-        if throttle > 1.0 || throttle < -1.0 {
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        if !(-1.0..=1.0).contains(&throttle) {
             return Err(MotorError::ThrottleError);
         }
         let duty_cycle = (4095.0 * throttle.abs()) as u16;
 
         match throttle.partial_cmp(&0.0) {
             Some(Ordering::Greater) => {
-                pwm.set_channel_off(self.channels.forward_channel, duty_cycle)
-                    .map_err(|_| MotorError::ChannelError)?;
+                self.drive(
+                    pwm,
+                    self.channels.forward_channel,
+                    self.channels.backward_channel,
+                    duty_cycle,
+                )?;
             }
             Some(Ordering::Less) => {
-                pwm.set_channel_off(self.channels.backward_channel, duty_cycle)
-                    .map_err(|_| MotorError::ChannelError)?;
+                self.drive(
+                    pwm,
+                    self.channels.backward_channel,
+                    self.channels.forward_channel,
+                    duty_cycle,
+                )?;
             }
             _ => {
                 pwm.set_channel_full_off(self.channels.forward_channel)
@@ -101,11 +136,42 @@ impl DcMotor {
         Ok(())
     }
 
+    /// Drives `primary` at `duty_cycle`. In `DecayMode::Slow`, `secondary`
+    /// is driven at `4095 - duty_cycle` for the rest of the PWM cycle so
+    /// both windings are shorted during the idle phase; in
+    /// `DecayMode::Fast`, `secondary` is explicitly set full-off so the
+    /// motor coasts, rather than left at whatever value a previous
+    /// `DecayMode::Slow` drive may have left it at.
+    fn drive<I2C, E>(
+        &self,
+        pwm: &mut Pca9685<I2C>,
+        primary: Channel,
+        secondary: Channel,
+        duty_cycle: u16,
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        pwm.set_channel_off(primary, duty_cycle)
+            .map_err(|_| MotorError::ChannelError)?;
+        if self.decay_mode == DecayMode::Slow {
+            pwm.set_channel_off(secondary, 4095 - duty_cycle)
+                .map_err(|_| MotorError::ChannelError)?;
+        } else {
+            pwm.set_channel_full_off(secondary)
+                .map_err(|_| MotorError::ChannelError)?;
+        }
+        Ok(())
+    }
+
     /// Stops energizing the PWMs for this motor.
-    pub fn stop(
+    pub fn stop<I2C, E>(
         &mut self,
-        pwm: &mut Pca9685<I2cdev>,
-    ) -> Result<(), MotorError> {
+        pwm: &mut Pca9685<I2C>,
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
         // Set the reference channel to run at full blast.
         pwm.set_channel_full_off(self.channels.ref_channel)
             .map_err(|_| MotorError::ChannelError)?;
@@ -1,10 +1,16 @@
+#[cfg(feature = "linux")]
 use hal::I2cdev;
+#[cfg(feature = "linux")]
 use linux_embedded_hal as hal;
+#[cfg(feature = "linux")]
 use pwm_pca9685::{Pca9685, SlaveAddr};
 use std::error::Error;
 use std::fmt;
 
+#[cfg(feature = "linux")]
+pub mod control;
 pub mod dc;
+pub mod servo;
 pub mod stepper;
 
 #[derive(Debug)]
@@ -21,6 +27,14 @@ pub enum MotorError {
     /// An invalid motor was provided to a constructor, i.e. a stepper motor
     /// passed into the DcMotor constructor.
     InvalidMotorError,
+    /// A required configuration value was missing, e.g. requesting an
+    /// RPM-based move before `steps_per_revolution` was set.
+    ConfigError,
+    /// The requested steps-per-second (or RPM) rate was not a finite,
+    /// positive value.
+    SpeedError,
+    /// The value for a servo angle is not in the bounds of [0.0, 180.0].
+    AngleError,
 }
 
 impl fmt::Display for MotorError {
@@ -31,7 +45,7 @@ impl fmt::Display for MotorError {
 
 impl Error for MotorError {}
 
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
 /// An enumeration of all potential motors that can be controlled via the
 /// Motor HAT.
 pub enum Motor {
@@ -43,25 +57,61 @@ pub enum Motor {
     Stepper2,
 }
 
-/// Initializes the PWM to control the Motor HAT. This makes a few assumptions:
-/// - Assumes only one Motor HAT as 0x96.
-/// - Assumes only a pre-scale of 4 so the HAT is running at ~1600 Hz.
+#[cfg(feature = "linux")]
+/// Computes the PCA9685 pre-scale register value for a requested PWM
+/// frequency, as `round(25_000_000 / (4096 * freq_hz)) - 1`. Returns
+/// `MotorError::PwmError` if the result falls outside the chip's valid
+/// range of 3-255.
+fn prescale_for_frequency(freq_hz: f32) -> Result<u8, MotorError> {
+    let prescale = (25_000_000.0 / (4096.0 * freq_hz)).round() - 1.0;
+    if !(3.0..=255.0).contains(&prescale) {
+        return Err(MotorError::PwmError);
+    }
+    Ok(prescale as u8)
+}
+
+#[cfg(feature = "linux")]
+/// The PWM frequency `init_pwm` runs the Motor HAT at by default, in Hz
+/// (~1220.7 Hz). This round-trips through `prescale_for_frequency` back
+/// to the pre-scale of 4 the crate has always used.
+pub const DEFAULT_FREQUENCY_HZ: f32 = 25_000_000.0 / (4096.0 * 5.0);
+
+#[cfg(feature = "linux")]
+/// The default address for the motor hat is 0x60.
+pub const DEFAULT_ADDRESS: SlaveAddr =
+    SlaveAddr::Alternative(true, false, false, false, false, false);
+
+#[cfg(feature = "linux")]
+/// Initializes the PWM to control the Motor HAT over a Linux `/dev/i2c-*`
+/// bus, at the default frequency (`DEFAULT_FREQUENCY_HZ`, ~1220.7 Hz) and
+/// address (0x60).
 ///
 /// If no I2C bus is provided to the function, it will attempt to
 /// connect to /dev/i2c-1 which will work in most cases.
 pub fn init_pwm(i2c: Option<I2cdev>) -> Result<Pca9685<I2cdev>, MotorError> {
+    init_pwm_with(i2c, DEFAULT_FREQUENCY_HZ, DEFAULT_ADDRESS)
+}
+
+#[cfg(feature = "linux")]
+/// Initializes the PWM to control a Motor HAT running at `freq_hz` and
+/// addressed at `addr`, over a Linux `/dev/i2c-*` bus.
+///
+/// If no I2C bus is provided to the function, it will attempt to
+/// connect to /dev/i2c-1 which will work in most cases.
+pub fn init_pwm_with(
+    i2c: Option<I2cdev>,
+    freq_hz: f32,
+    addr: SlaveAddr,
+) -> Result<Pca9685<I2cdev>, MotorError> {
     let i2c = if let Some(i2c) = i2c {
         i2c
     } else {
         I2cdev::new("/dev/i2c-1").map_err(|_| MotorError::I2cError)?
     };
 
-    // The default address for the motor hat is 0x96.
-    let address =
-        SlaveAddr::Alternative(true, false, false, false, false, false);
-
-    let mut pwm = Pca9685::new(i2c, address);
+    let prescale = prescale_for_frequency(freq_hz)?;
+    let mut pwm = Pca9685::new(i2c, addr);
     pwm.enable().map_err(|_| MotorError::PwmError)?;
-    pwm.set_prescale(4).map_err(|_| MotorError::PwmError)?;
+    pwm.set_prescale(prescale).map_err(|_| MotorError::PwmError)?;
     Ok(pwm)
 }
@@ -0,0 +1,109 @@
+use crate::MotorError;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use pwm_pca9685::{Channel, Pca9685};
+
+/// Default pulse width, in microseconds, for a 0 degree angle.
+pub const DEFAULT_MIN_PULSE_US: f32 = 1000.0;
+/// Default pulse width, in microseconds, for a 180 degree angle.
+pub const DEFAULT_MAX_PULSE_US: f32 = 2000.0;
+
+/// A structure to initialize and control an RC servo driven directly off a
+/// PCA9685 channel, rather than through one of the Motor HAT's DC/stepper
+/// channel maps.
+pub struct ServoMotor {
+    channel: Channel,
+    freq_hz: f32,
+    min_pulse_us: f32,
+    max_pulse_us: f32,
+}
+
+impl ServoMotor {
+    /// Attempts to initialize a servo on `channel`, driven at `freq_hz`
+    /// (the PWM frequency the hat was configured for via
+    /// `init_pwm`/`init_pwm_with`). Uses `DEFAULT_MIN_PULSE_US` and
+    /// `DEFAULT_MAX_PULSE_US` for the 0-180 degree pulse range; use
+    /// `try_new_with_pulse_range` to override them.
+    pub fn try_new<I2C, E>(
+        pwm: &mut Pca9685<I2C>,
+        channel: Channel,
+        freq_hz: f32,
+    ) -> Result<Self, MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        Self::try_new_with_pulse_range(
+            pwm,
+            channel,
+            freq_hz,
+            DEFAULT_MIN_PULSE_US,
+            DEFAULT_MAX_PULSE_US,
+        )
+    }
+
+    /// Like `try_new`, but with explicit minimum/maximum pulse widths (in
+    /// microseconds) spanning the 0-180 degree range.
+    pub fn try_new_with_pulse_range<I2C, E>(
+        pwm: &mut Pca9685<I2C>,
+        channel: Channel,
+        freq_hz: f32,
+        min_pulse_us: f32,
+        max_pulse_us: f32,
+    ) -> Result<Self, MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        pwm.set_channel_on(channel, 0)
+            .map_err(|_| MotorError::ChannelError)?;
+        Ok(Self {
+            channel,
+            freq_hz,
+            min_pulse_us,
+            max_pulse_us,
+        })
+    }
+
+    /// Points the servo at `angle_deg`. Valid angles are in the range
+    /// [0.0, 180.0].
+    pub fn set_angle<I2C, E>(
+        &mut self,
+        pwm: &mut Pca9685<I2C>,
+        angle_deg: f32,
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        if !(0.0..=180.0).contains(&angle_deg) {
+            return Err(MotorError::AngleError);
+        }
+        let pulse_us = self.min_pulse_us
+            + (self.max_pulse_us - self.min_pulse_us) * (angle_deg / 180.0);
+        self.set_pulse_us(pwm, pulse_us)
+    }
+
+    /// Drives the servo with an explicit pulse width, in microseconds.
+    pub fn set_pulse_us<I2C, E>(
+        &mut self,
+        pwm: &mut Pca9685<I2C>,
+        pulse_us: f32,
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        let counts =
+            (pulse_us * self.freq_hz * 4096.0 / 1_000_000.0).round() as u16;
+        pwm.set_channel_off(self.channel, counts.min(4095))
+            .map_err(|_| MotorError::ChannelError)
+    }
+
+    /// Stops driving the servo, releasing it to move freely.
+    pub fn disable<I2C, E>(
+        &mut self,
+        pwm: &mut Pca9685<I2C>,
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        pwm.set_channel_full_off(self.channel)
+            .map_err(|_| MotorError::ChannelError)
+    }
+}
@@ -1,10 +1,10 @@
 use crate::{Motor, MotorError};
-use hal::I2cdev;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
 use lazy_static::lazy_static;
-use linux_embedded_hal as hal;
 use pwm_pca9685::{Channel, Pca9685};
 use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::time::{Duration, Instant};
 
 lazy_static! {
     pub static ref STEP_CHANNEL_MAP: HashMap<Motor, StepChannels> = {
@@ -45,13 +45,27 @@ pub struct StepChannels {
     bin1: Channel,
     bin2: Channel,
 }
-#[derive(PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum StepDirection {
     Forward,
     Backward,
 }
 
-#[derive(Debug, PartialEq)]
+/// Selects how a stepper motor's four H-bridge channels are wired to its
+/// coils, which determines the order `update_coils` commutates them in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StepperWiring {
+    /// A bipolar 2-coil motor, where `ain1`/`ain2` and `bin1`/`bin2` are
+    /// each a push-pull polarity pair for one winding.
+    Bipolar,
+    /// A unipolar 5/6-wire motor, where each channel energizes a single
+    /// coil tap in one direction only; the same microstep curve applies
+    /// per tap, but taps are commutated in the standard unipolar
+    /// full-step/half-step sequence rather than as polarity pairs.
+    Unipolar,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum StepStyle {
     Single,
     Double,
@@ -59,22 +73,136 @@ pub enum StepStyle {
     Microstep,
 }
 
+/// Reports whether a profiled move started with `step_to` or `run_at` is
+/// still in progress.
+#[derive(Debug, PartialEq)]
+pub enum MoveStatus {
+    /// The move is still running; call `poll` again no later than the
+    /// returned `Instant` to keep the ramp on schedule.
+    Running(Instant),
+    /// `step_to`'s target has been reached, or no move was in progress.
+    Complete,
+}
+
+/// A trapezoidal (accelerate/cruise/decelerate) velocity profile for a
+/// `StepperMotor` move, using the Austin approximation to derive each
+/// inter-step delay from the target `max_speed` (steps/s) and
+/// `acceleration` (steps/s^2).
+struct MotionProfile {
+    direction: StepDirection,
+    step_style: StepStyle,
+    /// Steps left to take; `None` means run indefinitely at `max_speed`
+    /// (used by `run_at`) rather than stopping at a fixed target.
+    steps_remaining: Option<i32>,
+    n: i32,
+    c: f32,
+    min_delay: f32,
+    acceleration: f32,
+    decelerating: bool,
+    due_at: Instant,
+}
+
+impl MotionProfile {
+    fn new(
+        direction: StepDirection,
+        step_style: StepStyle,
+        steps_remaining: Option<i32>,
+        max_speed: f32,
+        acceleration: f32,
+        now: Instant,
+    ) -> Self {
+        let c0 = 0.676 * (2.0 / acceleration).sqrt();
+        MotionProfile {
+            direction,
+            step_style,
+            steps_remaining,
+            // The first step's delay (`c0`) is scheduled directly as
+            // `due_at` below rather than through `next_delay`, so `n`
+            // starts at 1: the step that delay is already accounted for.
+            n: 1,
+            c: c0,
+            min_delay: 1.0 / max_speed,
+            acceleration,
+            decelerating: false,
+            due_at: now + Duration::from_secs_f32(c0),
+        }
+    }
+
+    /// Advances the ramp by one step, returning the delay before the step
+    /// after that, or `None` if the target has been reached.
+    fn next_delay(&mut self) -> Option<Duration> {
+        if let Some(steps_remaining) = self.steps_remaining {
+            if steps_remaining <= 0 {
+                return None;
+            }
+        }
+
+        if let Some(steps_remaining) = self.steps_remaining {
+            let velocity = 1.0 / self.c;
+            let steps_to_stop =
+                (velocity * velocity) / (2.0 * self.acceleration);
+            if !self.decelerating && steps_remaining as f32 <= steps_to_stop {
+                self.decelerating = true;
+                // Re-seed n at the mirror point of the ramp (the classic
+                // Austin/AccelStepper trick) rather than letting it carry
+                // over from wherever cruise left it. n keeps climbing
+                // through however long the move cruises at max_speed, so
+                // without this the decel recurrence below sees a huge n
+                // and applies vanishingly small increments, slamming to a
+                // stop instead of decelerating. Reusing the same formula
+                // with a negative n keeps decel symmetric with accel.
+                self.n = -(steps_to_stop.round() as i32);
+            }
+        }
+
+        self.c -= (2.0 * self.c) / (4.0 * self.n as f32 + 1.0);
+        self.c = self.c.max(self.min_delay);
+
+        self.n += 1;
+        if let Some(steps_remaining) = self.steps_remaining.as_mut() {
+            *steps_remaining -= 1;
+        }
+        Some(Duration::from_secs_f32(self.c))
+    }
+}
+
+/// A fixed-rate run for a `StepperMotor`, started by `run_at_speed` or
+/// `run_at_rpm`. Unlike `MotionProfile`, there's no acceleration ramp or
+/// target position: it holds a constant rate until `stop` or another
+/// `run_at_speed`/`run_at_rpm` call replaces it. A `step_to`/`run_at`
+/// move is tracked separately and does not cancel it.
+struct ContinuousRun {
+    direction: StepDirection,
+    step_style: StepStyle,
+    interval: Duration,
+    due_at: Instant,
+}
+
 /// A structure to initialize and control a stepper motor.
 pub struct StepperMotor {
     microsteps: i32,
     pub channels: StepChannels,
     curve: Vec<i32>,
     current_step: i32,
+    motion: Option<MotionProfile>,
+    continuous: Option<ContinuousRun>,
+    steps_per_revolution: Option<i32>,
+    wiring: StepperWiring,
 }
 
 impl StepperMotor {
     /// Initializes the stepper motor. If `microsteps` is not specified, it
-    /// defaults to 16.
-    pub fn try_new(
-        pwm: &mut Pca9685<I2cdev>,
+    /// defaults to 16. `wiring` selects whether the four channels drive a
+    /// bipolar (push-pull) or unipolar (single-direction tap) motor.
+    pub fn try_new<I2C, E>(
+        pwm: &mut Pca9685<I2C>,
         step_motor: Motor,
         microsteps: Option<i32>,
-    ) -> Result<Self, MotorError> {
+        wiring: StepperWiring,
+    ) -> Result<Self, MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
         let channels = STEP_CHANNEL_MAP.get(&step_motor).unwrap();
         let microsteps = microsteps.unwrap_or(16);
         let curve: Vec<i32> = (0..microsteps + 1)
@@ -113,6 +241,10 @@ impl StepperMotor {
             current_step: i32::MAX/2,
             channels: (*channels).clone(),
             curve,
+            motion: None,
+            continuous: None,
+            steps_per_revolution: None,
+            wiring,
         };
         stepper.update_coils(pwm, [0; 4])?;
         Ok(stepper)
@@ -120,23 +252,201 @@ impl StepperMotor {
 
     /// Commands the stepper motor to step one time in a given direction and
     /// with a given style.
-    pub fn step_once(
+    pub fn step_once<I2C, E>(
         &mut self,
-        pwm: &mut Pca9685<I2cdev>,
+        pwm: &mut Pca9685<I2C>,
         step_dir: StepDirection,
         step_style: StepStyle,
-    ) -> Result<(), MotorError> {
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
         // Set the reference channels to run at full blast.
         let duty_cycle = self.calc_step(step_dir, step_style)?;
         self.update_coils(pwm, duty_cycle)?;
         Ok(())
     }
 
-    /// Stops energizing the PWMs for this motor.
-    pub fn stop(
+    /// Begins a trapezoidal-profile move to `target_step` (an absolute
+    /// position on the motor's internal step counter), ramping up to
+    /// `max_speed` (steps/s) at `acceleration` (steps/s^2) and back down
+    /// again. The move takes one `step_style` step per unit of distance
+    /// between the counter and `target_step`, so it stops exactly on
+    /// target only with `StepStyle::Microstep`; the other styles advance
+    /// the counter by more than one per step. Drive the move forward
+    /// by calling `poll` with the current time until it reports
+    /// `MoveStatus::Complete`.
+    pub fn step_to(
+        &mut self,
+        target_step: i32,
+        step_style: StepStyle,
+        max_speed: f32,
+        acceleration: f32,
+        now: Instant,
+    ) {
+        let delta = target_step - self.current_step;
+        let direction = if delta >= 0 {
+            StepDirection::Forward
+        } else {
+            StepDirection::Backward
+        };
+        self.motion = Some(MotionProfile::new(
+            direction,
+            step_style,
+            Some(delta.abs()),
+            max_speed,
+            acceleration,
+            now,
+        ));
+    }
+
+    /// Begins (or re-targets) an open-ended move that ramps up to
+    /// `max_speed` (steps/s) in `step_dir` at `acceleration` (steps/s^2)
+    /// and holds that speed until `stop` or another `step_to`/`run_at`
+    /// call replaces it. Drive the move forward by calling `poll` with
+    /// the current time.
+    pub fn run_at(
         &mut self,
-        pwm: &mut Pca9685<I2cdev>,
+        step_dir: StepDirection,
+        step_style: StepStyle,
+        max_speed: f32,
+        acceleration: f32,
+        now: Instant,
+    ) {
+        self.motion = Some(MotionProfile::new(
+            step_dir,
+            step_style,
+            None,
+            max_speed,
+            acceleration,
+            now,
+        ));
+    }
+
+    /// Advances a move started with `step_to` or `run_at` if its next step
+    /// is due by `now`, performing at most one `step_once` per call.
+    /// Returns `MoveStatus::Complete` once a `step_to` target has been
+    /// reached (or if no move is in progress), otherwise
+    /// `MoveStatus::Running` with the `Instant` the next step falls due.
+    pub fn poll<I2C, E>(
+        &mut self,
+        pwm: &mut Pca9685<I2C>,
+        now: Instant,
+    ) -> Result<MoveStatus, MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        let Some(motion) = self.motion.as_mut() else {
+            return Ok(MoveStatus::Complete);
+        };
+
+        if now < motion.due_at {
+            return Ok(MoveStatus::Running(motion.due_at));
+        }
+
+        let direction = motion.direction;
+        let step_style = motion.step_style;
+        match motion.next_delay() {
+            Some(delay) => {
+                self.step_once(pwm, direction, step_style)?;
+                let motion = self.motion.as_mut().unwrap();
+                motion.due_at = now + delay;
+                Ok(MoveStatus::Running(motion.due_at))
+            }
+            None => {
+                self.motion = None;
+                Ok(MoveStatus::Complete)
+            }
+        }
+    }
+
+    /// Sets the number of whole steps per revolution for this motor, used
+    /// to convert an RPM target into a steps-per-second rate in
+    /// `run_at_rpm`.
+    pub fn set_steps_per_revolution(&mut self, steps_per_revolution: i32) {
+        self.steps_per_revolution = Some(steps_per_revolution);
+    }
+
+    /// Begins (or re-targets) a continuous run at a constant
+    /// `steps_per_second` in `step_dir`, holding that rate until `stop` or
+    /// another `run_at_speed`/`run_at_rpm` call replaces it; a
+    /// `step_to`/`run_at` move does not cancel it. Drive the run forward by calling
+    /// `tick` with the current time. Returns `MotorError::SpeedError` if
+    /// `steps_per_second` isn't finite and positive.
+    pub fn run_at_speed(
+        &mut self,
+        step_dir: StepDirection,
+        step_style: StepStyle,
+        steps_per_second: f32,
+        now: Instant,
     ) -> Result<(), MotorError> {
+        if !steps_per_second.is_finite() || steps_per_second <= 0.0 {
+            return Err(MotorError::SpeedError);
+        }
+        self.continuous = Some(ContinuousRun {
+            direction: step_dir,
+            step_style,
+            interval: Duration::from_secs_f32(1.0 / steps_per_second),
+            due_at: now,
+        });
+        Ok(())
+    }
+
+    /// Like `run_at_speed`, but takes a target `rpm` and converts it using
+    /// `steps_per_revolution` (set via `set_steps_per_revolution`).
+    /// Returns `MotorError::ConfigError` if `steps_per_revolution` hasn't
+    /// been set.
+    pub fn run_at_rpm(
+        &mut self,
+        step_dir: StepDirection,
+        step_style: StepStyle,
+        rpm: f32,
+        now: Instant,
+    ) -> Result<(), MotorError> {
+        let steps_per_revolution =
+            self.steps_per_revolution.ok_or(MotorError::ConfigError)?;
+        let steps_per_second = rpm * steps_per_revolution as f32 / 60.0;
+        self.run_at_speed(step_dir, step_style, steps_per_second, now)
+    }
+
+    /// Advances a run started with `run_at_speed`/`run_at_rpm` if its next
+    /// step is due by `now`, performing at most one `step_once` per call.
+    /// The following step is scheduled from the previous due time rather
+    /// than from `now`, so the average rate holds even if `tick` is called
+    /// late or irregularly. Returns `false` if no continuous run is in
+    /// progress.
+    pub fn tick<I2C, E>(
+        &mut self,
+        pwm: &mut Pca9685<I2C>,
+        now: Instant,
+    ) -> Result<bool, MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        let Some(run) = self.continuous.as_ref() else {
+            return Ok(false);
+        };
+        if now < run.due_at {
+            return Ok(true);
+        }
+
+        let (direction, step_style, interval, due_at) =
+            (run.direction, run.step_style, run.interval, run.due_at);
+        self.step_once(pwm, direction, step_style)?;
+        if let Some(run) = self.continuous.as_mut() {
+            run.due_at = due_at + interval;
+        }
+        Ok(true)
+    }
+
+    /// Stops energizing the PWMs for this motor.
+    pub fn stop<I2C, E>(
+        &mut self,
+        pwm: &mut Pca9685<I2C>,
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
         pwm.set_channel_full_off(self.channels.ref_channel1)
             .map_err(|_| MotorError::ChannelError)?;
         pwm.set_channel_full_off(self.channels.ref_channel1)
@@ -149,6 +459,8 @@ impl StepperMotor {
             .map_err(|_| MotorError::ChannelError)?;
         pwm.set_channel_full_off(self.channels.bin2)
             .map_err(|_| MotorError::ChannelError)?;
+        self.motion = None;
+        self.continuous = None;
         Ok(())
     }
 
@@ -162,23 +474,46 @@ impl StepperMotor {
             StepDirection::Forward => self.current_step += step_size,
             StepDirection::Backward => self.current_step -= step_size,
         }
-        let duty_cycles = self.calc_duty_cycle(step_style);
+        let duty_cycles = match self.wiring {
+            StepperWiring::Bipolar => self.calc_duty_cycle(step_style),
+            StepperWiring::Unipolar => self.calc_duty_cycle_unipolar(step_style),
+        };
         Ok(duty_cycles)
     }
 
-    fn update_coils(
+    fn update_coils<I2C, E>(
         &mut self,
-        pwm: &mut Pca9685<I2cdev>,
+        pwm: &mut Pca9685<I2C>,
         duty_cycle: [i32; 4],
-    ) -> Result<(), MotorError> {
-        pwm.set_channel_off(self.channels.ain2, duty_cycle[0] as u16)
-            .map_err(|_| MotorError::ChannelError)?;
-        pwm.set_channel_off(self.channels.bin1, duty_cycle[1] as u16)
-            .map_err(|_| MotorError::ChannelError)?;
-        pwm.set_channel_off(self.channels.ain1, duty_cycle[2] as u16)
-            .map_err(|_| MotorError::ChannelError)?;
-        pwm.set_channel_off(self.channels.bin2, duty_cycle[3] as u16)
-            .map_err(|_| MotorError::ChannelError)?;
+    ) -> Result<(), MotorError>
+    where
+        I2C: Write<Error = E> + WriteRead<Error = E>,
+    {
+        // Bipolar wiring pairs ain1/ain2 and bin1/bin2 as the two
+        // polarities of each winding, so the commutation order interleaves
+        // them; unipolar wiring drives each of the four coil taps
+        // independently, so the taps are commutated in their natural
+        // order instead. `calc_duty_cycle`/`calc_duty_cycle_unipolar` below
+        // compute the values themselves per wiring; this mapping only picks
+        // which physical channel each of the four slots lands on.
+        let order = match self.wiring {
+            StepperWiring::Bipolar => [
+                self.channels.ain2,
+                self.channels.bin1,
+                self.channels.ain1,
+                self.channels.bin2,
+            ],
+            StepperWiring::Unipolar => [
+                self.channels.ain1,
+                self.channels.bin1,
+                self.channels.ain2,
+                self.channels.bin2,
+            ],
+        };
+        for (channel, value) in order.iter().zip(duty_cycle.iter()) {
+            pwm.set_channel_off(*channel, *value as u16)
+                .map_err(|_| MotorError::ChannelError)?;
+        }
 
         Ok(())
     }
@@ -187,7 +522,7 @@ impl StepperMotor {
         let mut duty_cycles = [0; 4];
         let trailing_coil =
             ((self.current_step / self.microsteps) % 4) as usize;
-        let leading_coil = ((trailing_coil + 1) % 4) as usize;
+        let leading_coil = (trailing_coil + 1) % 4;
         let microstep = (self.current_step % self.microsteps) as usize;
         duty_cycles[leading_coil] = self.curve[microstep];
         duty_cycles[trailing_coil] =
@@ -202,6 +537,46 @@ impl StepperMotor {
         duty_cycles
     }
 
+    /// Like `calc_duty_cycle`, but for `StepperWiring::Unipolar`: each coil
+    /// tap is its own independent winding half rather than one side of a
+    /// push-pull pair, so there's no opposite-polarity partner to blend
+    /// against or collapse into. `Microstep` still blends the sine curve
+    /// between the two adjacent taps (both are driven positive, so the
+    /// blend is still valid); `Single` energizes one tap at a time
+    /// (wave drive), `Double` energizes the two adjacent taps together at
+    /// full duty (the standard unipolar full-step commutation), and
+    /// `Interleave` alternates between one tap on whole steps and two taps
+    /// on half steps (half-step commutation), rather than relying on the
+    /// curve happening to cross over at 4095 the way bipolar does.
+    fn calc_duty_cycle_unipolar(&mut self, step_style: StepStyle) -> [i32; 4] {
+        let mut duty_cycles = [0; 4];
+        let trailing_coil =
+            ((self.current_step / self.microsteps) % 4) as usize;
+        let leading_coil = (trailing_coil + 1) % 4;
+        let microstep = (self.current_step % self.microsteps) as usize;
+        match step_style {
+            StepStyle::Microstep => {
+                duty_cycles[leading_coil] = self.curve[microstep];
+                duty_cycles[trailing_coil] =
+                    self.curve[self.microsteps as usize - microstep];
+            }
+            StepStyle::Single => {
+                duty_cycles[trailing_coil] = 4095;
+            }
+            StepStyle::Double => {
+                duty_cycles[trailing_coil] = 4095;
+                duty_cycles[leading_coil] = 4095;
+            }
+            StepStyle::Interleave => {
+                duty_cycles[trailing_coil] = 4095;
+                if microstep != 0 {
+                    duty_cycles[leading_coil] = 4095;
+                }
+            }
+        }
+        duty_cycles
+    }
+
     fn calc_step_size(
         &mut self,
         step_dir: &StepDirection,
@@ -235,3 +610,42 @@ impl StepperMotor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decelerates_symmetrically_after_a_cruise_phase() {
+        let now = Instant::now();
+        let mut profile = MotionProfile::new(
+            StepDirection::Forward,
+            StepStyle::Microstep,
+            Some(5000),
+            2000.0,
+            4000.0,
+            now,
+        );
+
+        let mut last_delay = None;
+        let mut cruised = false;
+        while let Some(delay) = profile.next_delay() {
+            if delay.as_secs_f32() <= 1.0 / 2000.0 + 1e-6 {
+                cruised = true;
+            }
+            last_delay = Some(delay);
+        }
+
+        assert!(cruised, "move should reach cruise speed before decelerating");
+        // A move that cruised should still decelerate back down to a near
+        // standstill rather than slamming to a stop at ~cruise speed: the
+        // last inter-step delay should mirror the ramp-up's second delay
+        // (~0.009s for these parameters), not the ~0.0005s cruise interval.
+        let last_delay = last_delay.expect("move should take at least one step");
+        assert!(
+            last_delay.as_secs_f32() > 0.005,
+            "expected the move to decelerate to a near standstill, last delay was {:?}",
+            last_delay
+        );
+    }
+}